@@ -0,0 +1,488 @@
+//! Compressed, self-describing snapshots of Claude session files.
+//!
+//! Replaces the ad-hoc `.jsonl.bak` copies produced by the thinking-blocks fix with a
+//! single gzip-compressed tar archive per run, paired with a `metadata.json` manifest
+//! describing what was captured. Snapshots are written to
+//! `~/.claude/cc-switch-snapshots/<seconds>-<nanos>-<sequence>.tar.gz` and can be rolled
+//! back with [`restore_snapshot`].
+
+use crate::commands::thinking_fix::ThinkingFixResult;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tar::Archive;
+
+/// Monotonic counter guaranteeing unique snapshot ids even when several snapshots are
+/// created within the same system-clock tick (e.g. back-to-back files in a batch fix)
+static SNAPSHOT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Metadata about a single on-disk snapshot archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotBackupInfo {
+    /// Archive file name (e.g. "1753510000-482910000-3.tar.gz")
+    pub name: String,
+    /// Full path to the archive
+    pub path: String,
+    /// Archive size in bytes
+    pub size_bytes: u64,
+    /// Last-modified time as Unix timestamp (milliseconds)
+    pub modified_at: u64,
+}
+
+const MANIFEST_FILE_NAME: &str = "metadata.json";
+
+/// A single JSONL file captured inside a snapshot, and where it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFileEntry {
+    /// Absolute path the file was captured from, used to restore it back in place
+    pub original_path: String,
+    /// Fix result recorded for this file at snapshot time
+    pub result: ThinkingFixResult,
+}
+
+/// Manifest stored as `metadata.json` alongside the archived files in a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// cc-switch version that produced the snapshot
+    pub crate_version: String,
+    /// Unix timestamp (seconds) the snapshot was taken
+    pub created_at: u64,
+    /// Project directory the snapshot originated from
+    pub project_path: String,
+    /// The files captured in this snapshot
+    pub files: Vec<SnapshotFileEntry>,
+}
+
+/// Outcome of restoring a snapshot archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotRestoreResult {
+    /// Absolute paths that were overwritten with their snapshotted contents
+    pub restored_files: Vec<String>,
+    /// The manifest read back from the archive
+    pub manifest: SnapshotManifest,
+}
+
+/// Directory that holds all snapshot archives
+fn snapshots_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".claude")
+        .join("cc-switch-snapshots")
+}
+
+/// Write a gzip-compressed tar snapshot of `entries`' current on-disk contents
+///
+/// Stages the original files plus a `metadata.json` manifest in a temp directory, then
+/// streams that directory through a [`GzEncoder`] into the tar archive. Returns the path
+/// to the written `.tar.gz`.
+pub fn create_snapshot(project_path: &str, entries: &[SnapshotFileEntry]) -> Result<PathBuf, String> {
+    let snapshots_dir = snapshots_dir();
+    fs::create_dir_all(&snapshots_dir)
+        .map_err(|e| format!("Failed to create snapshots directory: {e}"))?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("Failed to read system time: {e}"))?;
+    let created_at = now.as_secs();
+
+    // Disambiguate archives with a nanosecond timestamp plus a monotonic counter, since
+    // back-to-back snapshots (e.g. from fix_thinking_blocks_all) can land in the same second.
+    let sequence = SNAPSHOT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let snapshot_id = format!("{created_at}-{}-{sequence}", now.subsec_nanos());
+
+    let archive_path = snapshots_dir.join(format!("{snapshot_id}.tar.gz"));
+
+    let staging_dir = std::env::temp_dir().join(format!("cc-switch-snapshot-{snapshot_id}"));
+    fs::create_dir_all(&staging_dir)
+        .map_err(|e| format!("Failed to create staging directory: {e}"))?;
+
+    for entry in entries {
+        let source = Path::new(&entry.original_path);
+        let file_name = source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| format!("Invalid file path: {}", entry.original_path))?;
+        fs::copy(source, staging_dir.join(file_name))
+            .map_err(|e| format!("Failed to stage {}: {e}", entry.original_path))?;
+    }
+
+    let manifest = SnapshotManifest {
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at,
+        project_path: project_path.to_string(),
+        files: entries.to_vec(),
+    };
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize snapshot manifest: {e}"))?;
+    fs::write(staging_dir.join(MANIFEST_FILE_NAME), manifest_json)
+        .map_err(|e| format!("Failed to write snapshot manifest: {e}"))?;
+
+    let archive_file = File::create(&archive_path)
+        .map_err(|e| format!("Failed to create snapshot archive: {e}"))?;
+    let encoder = GzEncoder::new(archive_file, Compression::default());
+    let mut tar_builder = tar::Builder::new(encoder);
+    tar_builder
+        .append_dir_all(".", &staging_dir)
+        .map_err(|e| format!("Failed to write snapshot archive: {e}"))?;
+    tar_builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|e| format!("Failed to finalize snapshot archive: {e}"))?;
+
+    fs::remove_dir_all(&staging_dir).ok();
+
+    Ok(archive_path)
+}
+
+/// Read just the `metadata.json` manifest out of a snapshot archive, without extracting it
+fn read_manifest(archive_path: &Path) -> Result<SnapshotManifest, String> {
+    let archive_file =
+        File::open(archive_path).map_err(|e| format!("Failed to open snapshot archive: {e}"))?;
+    let decoder = GzDecoder::new(archive_file);
+    let mut archive = Archive::new(decoder);
+
+    for entry in archive
+        .entries()
+        .map_err(|e| format!("Failed to read snapshot archive: {e}"))?
+    {
+        let mut entry = entry.map_err(|e| format!("Failed to read snapshot archive entry: {e}"))?;
+        if entry.path().ok().as_deref() == Some(Path::new(MANIFEST_FILE_NAME)) {
+            let mut manifest_json = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut manifest_json)
+                .map_err(|e| format!("Failed to read snapshot manifest: {e}"))?;
+            return serde_json::from_str(&manifest_json)
+                .map_err(|e| format!("Failed to parse snapshot manifest: {e}"));
+        }
+    }
+
+    Err(format!(
+        "Snapshot archive {} has no manifest",
+        archive_path.display()
+    ))
+}
+
+/// List snapshot archives recorded against a given project, newest first
+#[tauri::command]
+pub async fn list_thinking_backups(project_path: String) -> Result<Vec<SnapshotBackupInfo>, String> {
+    list_backups_for_project(&project_path)
+}
+
+fn list_backups_for_project(project_path: &str) -> Result<Vec<SnapshotBackupInfo>, String> {
+    let snapshots_dir = snapshots_dir();
+    if !snapshots_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+
+    let entries = fs::read_dir(&snapshots_dir)
+        .map_err(|e| format!("Failed to read snapshots directory: {e}"))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("gz") {
+            continue;
+        }
+
+        let manifest = match read_manifest(&path) {
+            Ok(manifest) => manifest,
+            Err(_) => continue,
+        };
+        if manifest.project_path != project_path {
+            continue;
+        }
+
+        let metadata = fs::metadata(&path).ok();
+        let size_bytes = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+        let modified_at = metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        backups.push(SnapshotBackupInfo {
+            name: path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_string(),
+            path: path.to_string_lossy().to_string(),
+            size_bytes,
+            modified_at,
+        });
+    }
+
+    backups.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+
+    Ok(backups)
+}
+
+/// Restore a single file from a snapshot archive over a chosen target path
+///
+/// Validates that the archived copy parses as JSONL before overwriting `target_path`, so a
+/// corrupt or unrelated snapshot can't clobber a live session file.
+#[tauri::command]
+pub async fn restore_thinking_backup(
+    backup_path: String,
+    target_path: String,
+) -> Result<String, String> {
+    let backup_path = PathBuf::from(&backup_path);
+    let target_path = PathBuf::from(&target_path);
+    let file_name = target_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("Invalid target path: {}", target_path.display()))?;
+
+    let archive_file =
+        File::open(&backup_path).map_err(|e| format!("Failed to open snapshot archive: {e}"))?;
+    let decoder = GzDecoder::new(archive_file);
+    let mut archive = Archive::new(decoder);
+
+    let mut contents = None;
+    for entry in archive
+        .entries()
+        .map_err(|e| format!("Failed to read snapshot archive: {e}"))?
+    {
+        let mut entry = entry.map_err(|e| format!("Failed to read snapshot archive entry: {e}"))?;
+        if entry.path().ok().as_deref() == Some(Path::new(file_name)) {
+            let mut buf = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut buf)
+                .map_err(|e| format!("Failed to read {file_name} from snapshot: {e}"))?;
+            contents = Some(buf);
+            break;
+        }
+    }
+
+    let contents = contents.ok_or_else(|| {
+        format!(
+            "Snapshot archive does not contain a file named {file_name}"
+        )
+    })?;
+
+    for line in contents.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        serde_json::from_str::<serde_json::Value>(line)
+            .map_err(|e| format!("Backup failed JSONL validation: {e}"))?;
+    }
+
+    fs::write(&target_path, contents)
+        .map_err(|e| format!("Failed to restore {}: {e}", target_path.display()))?;
+
+    Ok(target_path.to_string_lossy().to_string())
+}
+
+/// Prune old snapshot archives for a project, keeping only the `keep` most recent
+///
+/// `exclude` lists archives (typically the ones just created by the current run) that are
+/// never eligible for removal, regardless of `keep` — only snapshots from prior runs are
+/// counted against the limit. This keeps a batch of M files sharing a `project_path` from
+/// deleting the very originals it just captured when `keep` is less than M.
+///
+/// Returns the number of archives removed.
+pub fn prune_old_snapshots(project_path: &str, keep: usize, exclude: &[PathBuf]) -> Result<usize, String> {
+    let snapshots_dir = snapshots_dir();
+    if !snapshots_dir.exists() {
+        return Ok(0);
+    }
+
+    let mut project_snapshots: Vec<(PathBuf, u64)> = fs::read_dir(&snapshots_dir)
+        .map_err(|e| format!("Failed to read snapshots directory: {e}"))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("gz"))
+        .filter(|path| !exclude.contains(path))
+        .filter_map(|path| {
+            let manifest = read_manifest(&path).ok()?;
+            if manifest.project_path != project_path {
+                return None;
+            }
+            Some((path, manifest.created_at))
+        })
+        .collect();
+
+    project_snapshots.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut removed = 0;
+    for (path, _) in project_snapshots.into_iter().skip(keep) {
+        if fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Restore the original files captured in a snapshot archive back to their recorded locations
+#[tauri::command]
+pub async fn restore_snapshot(archive_path: String) -> Result<SnapshotRestoreResult, String> {
+    restore_snapshot_from_path(&PathBuf::from(archive_path))
+}
+
+fn restore_snapshot_from_path(archive_path: &Path) -> Result<SnapshotRestoreResult, String> {
+    let extract_dir = std::env::temp_dir().join(format!(
+        "cc-switch-restore-{}",
+        archive_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("snapshot")
+    ));
+    fs::create_dir_all(&extract_dir)
+        .map_err(|e| format!("Failed to create extraction directory: {e}"))?;
+
+    let archive_file =
+        File::open(archive_path).map_err(|e| format!("Failed to open snapshot archive: {e}"))?;
+    let decoder = GzDecoder::new(archive_file);
+    let mut archive = Archive::new(decoder);
+    archive
+        .unpack(&extract_dir)
+        .map_err(|e| format!("Failed to extract snapshot archive: {e}"))?;
+
+    let manifest_json = fs::read_to_string(extract_dir.join(MANIFEST_FILE_NAME))
+        .map_err(|e| format!("Failed to read snapshot manifest: {e}"))?;
+    let manifest: SnapshotManifest = serde_json::from_str(&manifest_json)
+        .map_err(|e| format!("Failed to parse snapshot manifest: {e}"))?;
+
+    let mut restored_files = Vec::new();
+    for entry in &manifest.files {
+        let source = Path::new(&entry.original_path);
+        let file_name = source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| format!("Invalid recorded path: {}", entry.original_path))?;
+
+        fs::copy(extract_dir.join(file_name), source)
+            .map_err(|e| format!("Failed to restore {}: {e}", entry.original_path))?;
+        restored_files.push(entry.original_path.clone());
+    }
+
+    fs::remove_dir_all(&extract_dir).ok();
+
+    Ok(SnapshotRestoreResult {
+        restored_files,
+        manifest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn sample_result(jsonl_file: &str) -> ThinkingFixResult {
+        ThinkingFixResult {
+            total_lines: 1,
+            modified_lines: 1,
+            thinking_blocks_removed: 1,
+            removed_by_type: HashMap::new(),
+            errors: 0,
+            backup_path: None,
+            jsonl_file: jsonl_file.to_string(),
+            dry_run: false,
+            diff: None,
+        }
+    }
+
+    fn unique_test_dir(label: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let sequence = SNAPSHOT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("cc-switch-test-{label}-{nanos}-{sequence}"))
+    }
+
+    #[test]
+    fn create_and_restore_snapshot_round_trips_original_content() {
+        let project_dir = unique_test_dir("project");
+        fs::create_dir_all(&project_dir).unwrap();
+        let jsonl_path = project_dir.join("session.jsonl");
+        let original_content = r#"{"message":{"content":[{"type":"text","text":"hi"}]}}"#;
+        fs::write(&jsonl_path, original_content).unwrap();
+
+        let entry = SnapshotFileEntry {
+            original_path: jsonl_path.to_string_lossy().to_string(),
+            result: sample_result("session.jsonl"),
+        };
+        let project_path = project_dir.to_string_lossy().to_string();
+        let archive_path = create_snapshot(&project_path, &[entry]).unwrap();
+        assert!(archive_path.exists());
+
+        // Simulate the fix rewriting the live file after the snapshot was taken
+        fs::write(&jsonl_path, "{}").unwrap();
+
+        let restored = restore_snapshot_from_path(&archive_path).unwrap();
+        assert_eq!(restored.restored_files, vec![jsonl_path.to_string_lossy().to_string()]);
+        assert_eq!(fs::read_to_string(&jsonl_path).unwrap(), original_content);
+        assert_eq!(restored.manifest.project_path, project_path);
+
+        fs::remove_file(&archive_path).ok();
+        fs::remove_dir_all(&project_dir).ok();
+    }
+
+    #[test]
+    fn prune_old_snapshots_keeps_only_the_requested_count() {
+        let project_dir = unique_test_dir("prune-project");
+        fs::create_dir_all(&project_dir).unwrap();
+        let jsonl_path = project_dir.join("session.jsonl");
+        fs::write(&jsonl_path, "{}").unwrap();
+        let project_path = project_dir.to_string_lossy().to_string();
+
+        let mut archive_paths = Vec::new();
+        for _ in 0..3 {
+            let entry = SnapshotFileEntry {
+                original_path: jsonl_path.to_string_lossy().to_string(),
+                result: sample_result("session.jsonl"),
+            };
+            archive_paths.push(create_snapshot(&project_path, &[entry]).unwrap());
+        }
+
+        let removed = prune_old_snapshots(&project_path, 1, &[]).unwrap();
+        assert_eq!(removed, 2);
+
+        let remaining = list_backups_for_project(&project_path).unwrap();
+        assert_eq!(remaining.len(), 1);
+
+        for archive_path in &archive_paths {
+            fs::remove_file(archive_path).ok();
+        }
+        fs::remove_dir_all(&project_dir).ok();
+    }
+
+    #[test]
+    fn prune_old_snapshots_never_removes_excluded_archives() {
+        let project_dir = unique_test_dir("prune-exclude-project");
+        fs::create_dir_all(&project_dir).unwrap();
+        let jsonl_path = project_dir.join("session.jsonl");
+        fs::write(&jsonl_path, "{}").unwrap();
+        let project_path = project_dir.to_string_lossy().to_string();
+
+        let mut archive_paths = Vec::new();
+        for _ in 0..3 {
+            let entry = SnapshotFileEntry {
+                original_path: jsonl_path.to_string_lossy().to_string(),
+                result: sample_result("session.jsonl"),
+            };
+            archive_paths.push(create_snapshot(&project_path, &[entry]).unwrap());
+        }
+
+        // Even with keep=0, archives just created by this run (passed as `exclude`) must survive.
+        let removed = prune_old_snapshots(&project_path, 0, &archive_paths).unwrap();
+        assert_eq!(removed, 0);
+
+        let remaining = list_backups_for_project(&project_path).unwrap();
+        assert_eq!(remaining.len(), 3);
+
+        for archive_path in &archive_paths {
+            fs::remove_file(archive_path).ok();
+        }
+        fs::remove_dir_all(&project_dir).ok();
+    }
+}
@@ -2,9 +2,16 @@
 //!
 //! This module provides functionality to:
 //! - List Claude projects in ~/.claude/projects
-//! - Remove 'thinking' and 'redacted_thinking' blocks from session JSONL files
+//! - Remove configurable content blocks (by default 'thinking' and 'redacted_thinking')
+//!   from session JSONL files, optionally previewing or batching the change
+//!
+//! Before rewriting a session file, the original is captured via [`crate::commands::snapshot`]
+//! rather than a loose `.bak` copy.
 
+use crate::commands::snapshot::{self, SnapshotFileEntry};
+use ignore::WalkBuilder;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::time::SystemTime;
@@ -27,14 +34,95 @@ pub struct ThinkingFixResult {
     pub total_lines: usize,
     /// Number of lines that were modified
     pub modified_lines: usize,
-    /// Number of thinking blocks removed
+    /// Total number of blocks removed, across all matched types
     pub thinking_blocks_removed: usize,
+    /// Number of blocks removed, broken down by block `type`
+    pub removed_by_type: HashMap<String, usize>,
     /// Number of errors encountered
     pub errors: usize,
     /// Path to backup file (if created)
     pub backup_path: Option<String>,
     /// Name of the processed JSONL file
     pub jsonl_file: String,
+    /// Whether this result came from a dry run (no files written)
+    pub dry_run: bool,
+    /// Per-line preview of what would be (or was) removed, when requested
+    pub diff: Option<Vec<ThinkingFixDiffEntry>>,
+}
+
+/// Options controlling which content blocks a fix/preview pass strips
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixOptions {
+    /// Block `type` values to remove (defaults to "thinking" and "redacted_thinking")
+    #[serde(default = "default_block_types")]
+    pub block_types: Vec<String>,
+    /// Optional substring that a block's textual payload (`text`, `thinking`, `input`,
+    /// or `content`) must also contain to be removed. Matched against each field's
+    /// rendered value, not the block's raw JSON, so it won't trip on structural keys
+    /// like `"type"`.
+    #[serde(default)]
+    pub text_contains: Option<String>,
+}
+
+impl Default for FixOptions {
+    fn default() -> Self {
+        Self {
+            block_types: default_block_types(),
+            text_contains: None,
+        }
+    }
+}
+
+fn default_block_types() -> Vec<String> {
+    vec!["thinking".to_string(), "redacted_thinking".to_string()]
+}
+
+/// Field names that can carry a content block's textual payload, across the block types
+/// this module deals with (`text`/`thinking` blocks, and `tool_use`/`tool_result`, whose
+/// payload lives under `input`/`content` instead).
+const PAYLOAD_FIELDS: &[&str] = &["text", "thinking", "input", "content"];
+
+/// Render a block's textual payload fields for substring matching, skipping structural
+/// keys like `"type"` so a `text_contains` filter only matches what it looks like it should.
+fn block_payload_text(item: &serde_json::Value) -> String {
+    PAYLOAD_FIELDS
+        .iter()
+        .filter_map(|field| item.get(field))
+        .map(|value| match value.as_str() {
+            Some(s) => s.to_string(),
+            None => serde_json::to_string(value).unwrap_or_default(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Does a single content block match the configured removal criteria?
+fn block_matches(item: &serde_json::Value, options: &FixOptions) -> bool {
+    let type_matches = item
+        .get("type")
+        .and_then(|t| t.as_str())
+        .map(|t| options.block_types.iter().any(|bt| bt == t))
+        .unwrap_or(false);
+
+    if !type_matches {
+        return false;
+    }
+
+    match &options.text_contains {
+        None => true,
+        Some(substring) => block_payload_text(item).contains(substring.as_str()),
+    }
+}
+
+/// A single line-level change produced by a thinking-blocks fix/preview pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThinkingFixDiffEntry {
+    /// Zero-based index of the line within the JSONL file
+    pub line_index: usize,
+    /// Block `type` values removed from this line (e.g. "thinking")
+    pub removed_types: Vec<String>,
+    /// Short snippet of the surrounding message for context
+    pub snippet: String,
 }
 
 /// Get the Claude projects directory path
@@ -112,19 +200,48 @@ fn find_latest_jsonl(project_dir: &PathBuf) -> Option<PathBuf> {
     jsonl_files.first().map(|(path, _)| path.clone())
 }
 
-/// Fix thinking blocks in a Claude project's session file
-#[tauri::command]
-pub async fn fix_thinking_blocks(project_path: String) -> Result<ThinkingFixResult, String> {
-    let project_dir = PathBuf::from(&project_path);
+/// Maximum length of a diff snippet before it gets truncated
+const SNIPPET_MAX_LEN: usize = 80;
+
+/// Build a short, human-readable snippet of a message's text content for diff previews
+fn message_snippet(message: &serde_json::Value) -> String {
+    let text = message
+        .get("content")
+        .and_then(|c| c.as_array())
+        .and_then(|items| {
+            items.iter().find_map(|item| {
+                if item.get("type").and_then(|t| t.as_str()) == Some("text") {
+                    item.get("text").and_then(|t| t.as_str())
+                } else {
+                    None
+                }
+            })
+        })
+        .unwrap_or("")
+        .trim();
 
-    if !project_dir.exists() {
-        return Err(format!("Project directory not found: {project_path}"));
+    if text.chars().count() > SNIPPET_MAX_LEN {
+        let truncated: String = text.chars().take(SNIPPET_MAX_LEN).collect();
+        format!("{truncated}…")
+    } else {
+        text.to_string()
     }
+}
 
-    // Find the latest JSONL file
-    let jsonl_path = find_latest_jsonl(&project_dir)
-        .ok_or_else(|| "No .jsonl files found in project directory".to_string())?;
-
+/// Scan (and optionally rewrite) a single JSONL session file, removing matched content blocks
+///
+/// When `dry_run` is true, the file on disk is never touched; the result describes what
+/// would have changed. When `collect_diff` is true, a per-line diff listing is populated.
+/// Snapshot retention, if any, is the caller's responsibility: pruning here would be scoped
+/// to a single file and could delete snapshots that sibling files in the same batch just
+/// created (see `fix_thinking_blocks` and `fix_thinking_blocks_all`).
+fn scan_jsonl_file(
+    jsonl_path: &PathBuf,
+    project_path: &str,
+    options: &FixOptions,
+    dry_run: bool,
+    collect_diff: bool,
+) -> Result<ThinkingFixResult, String> {
     let jsonl_file = jsonl_path
         .file_name()
         .and_then(|n| n.to_str())
@@ -132,7 +249,7 @@ pub async fn fix_thinking_blocks(project_path: String) -> Result<ThinkingFixResu
         .to_string();
 
     // Read the file
-    let content = fs::read_to_string(&jsonl_path)
+    let content = fs::read_to_string(jsonl_path)
         .map_err(|e| format!("Failed to read JSONL file: {e}"))?;
 
     let lines: Vec<&str> = content.lines().collect();
@@ -140,9 +257,11 @@ pub async fn fix_thinking_blocks(project_path: String) -> Result<ThinkingFixResu
     let mut total_lines = 0;
     let mut modified_lines = 0;
     let mut thinking_blocks_removed = 0;
+    let mut removed_by_type: HashMap<String, usize> = HashMap::new();
     let mut errors = 0;
+    let mut diff = Vec::new();
 
-    for line in &lines {
+    for (index, line) in lines.iter().enumerate() {
         if line.trim().is_empty() {
             processed_lines.push(line.to_string());
             continue;
@@ -153,25 +272,41 @@ pub async fn fix_thinking_blocks(project_path: String) -> Result<ThinkingFixResu
         match serde_json::from_str::<serde_json::Value>(line) {
             Ok(mut data) => {
                 let original = serde_json::to_string(&data).unwrap_or_default();
+                let mut removed_types = Vec::new();
 
-                // Check for thinking blocks in message.content
-                if let Some(message) = data.get_mut("message") {
-                    if let Some(content) = message.get_mut("content") {
+                // Check for matched blocks in message.content
+                if let Some(message) = data.get("message").cloned() {
+                    if let Some(content) = data.get_mut("message").and_then(|m| m.get_mut("content")) {
                         if let Some(content_array) = content.as_array_mut() {
                             let original_len = content_array.len();
 
-                            // Filter out thinking blocks
-                            content_array.retain(|item| {
-                                if let Some(item_type) = item.get("type").and_then(|t| t.as_str()) {
-                                    if item_type == "thinking" || item_type == "redacted_thinking" {
-                                        return false;
+                            for item in content_array.iter() {
+                                if block_matches(item, options) {
+                                    let item_type = item
+                                        .get("type")
+                                        .and_then(|t| t.as_str())
+                                        .unwrap_or("unknown")
+                                        .to_string();
+                                    *removed_by_type.entry(item_type.clone()).or_insert(0) += 1;
+                                    if collect_diff {
+                                        removed_types.push(item_type);
                                     }
                                 }
-                                true
-                            });
+                            }
+
+                            // Filter out matched blocks
+                            content_array.retain(|item| !block_matches(item, options));
 
                             let removed = original_len - content_array.len();
                             thinking_blocks_removed += removed;
+
+                            if collect_diff && removed > 0 {
+                                diff.push(ThinkingFixDiffEntry {
+                                    line_index: index,
+                                    removed_types,
+                                    snippet: message_snippet(&message),
+                                });
+                            }
                         }
                     }
                 }
@@ -189,32 +324,232 @@ pub async fn fix_thinking_blocks(project_path: String) -> Result<ThinkingFixResu
         }
     }
 
-    // Create backup
-    let backup_path = jsonl_path.with_extension("jsonl.bak");
-    let backup_path_str = if backup_path.exists() {
-        // Add timestamp if backup already exists
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let new_backup = jsonl_path.with_extension(format!("{timestamp}.bak"));
-        fs::copy(&jsonl_path, &new_backup)
-            .map_err(|e| format!("Failed to create backup: {e}"))?;
-        Some(new_backup.to_string_lossy().to_string())
+    let backup_path_str = if dry_run {
+        None
     } else {
-        fs::copy(&jsonl_path, &backup_path)
-            .map_err(|e| format!("Failed to create backup: {e}"))?;
-        Some(backup_path.to_string_lossy().to_string())
+        let result_so_far = ThinkingFixResult {
+            total_lines,
+            modified_lines,
+            thinking_blocks_removed,
+            removed_by_type: removed_by_type.clone(),
+            errors,
+            backup_path: None,
+            jsonl_file: jsonl_file.clone(),
+            dry_run,
+            diff: None,
+        };
+
+        // Snapshot the original file before it gets rewritten
+        let snapshot_entry = SnapshotFileEntry {
+            original_path: jsonl_path.to_string_lossy().to_string(),
+            result: result_so_far,
+        };
+        let archive_path = snapshot::create_snapshot(project_path, std::slice::from_ref(&snapshot_entry))?;
+
+        // Write processed content
+        let output = processed_lines.join("\n");
+        fs::write(jsonl_path, output)
+            .map_err(|e| format!("Failed to write JSONL file: {e}"))?;
+
+        Some(archive_path.to_string_lossy().to_string())
     };
 
-    // Write processed content
-    let output = processed_lines.join("\n");
-    fs::write(&jsonl_path, output)
-        .map_err(|e| format!("Failed to write JSONL file: {e}"))?;
-
     Ok(ThinkingFixResult {
         total_lines,
         modified_lines,
         thinking_blocks_removed,
+        removed_by_type,
         errors,
         backup_path: backup_path_str,
         jsonl_file,
+        dry_run,
+        diff: if collect_diff { Some(diff) } else { None },
+    })
+}
+
+/// Fix thinking blocks in a Claude project's session file
+///
+/// When `dry_run` is true, the scan runs in full but the snapshot and rewrite are skipped,
+/// so the live session file is left untouched. When `retain_snapshots` is set, older
+/// snapshots for this project are pruned down to that count after the new one is written,
+/// with the snapshot just taken always excluded from the count so this call can never
+/// delete the original it just captured.
+#[tauri::command]
+pub async fn fix_thinking_blocks(
+    project_path: String,
+    dry_run: bool,
+    options: Option<FixOptions>,
+    retain_snapshots: Option<usize>,
+) -> Result<ThinkingFixResult, String> {
+    let project_dir = PathBuf::from(&project_path);
+
+    if !project_dir.exists() {
+        return Err(format!("Project directory not found: {project_path}"));
+    }
+
+    // Find the latest JSONL file
+    let jsonl_path = find_latest_jsonl(&project_dir)
+        .ok_or_else(|| "No .jsonl files found in project directory".to_string())?;
+
+    let options = options.unwrap_or_default();
+    let result = scan_jsonl_file(&jsonl_path, &project_path, &options, dry_run, dry_run)?;
+
+    if let (Some(keep), Some(archive_path)) = (retain_snapshots, &result.backup_path) {
+        snapshot::prune_old_snapshots(&project_path, keep, &[PathBuf::from(archive_path)])?;
+    }
+
+    Ok(result)
+}
+
+/// Preview what `fix_thinking_blocks` would change, without touching the session file
+///
+/// Always runs as a dry run and includes the per-line diff listing.
+#[tauri::command]
+pub async fn preview_thinking_fix(
+    project_path: String,
+    options: Option<FixOptions>,
+) -> Result<ThinkingFixResult, String> {
+    fix_thinking_blocks(project_path, true, options, None).await
+}
+
+/// Aggregated outcome of running a fix across multiple projects and/or session files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThinkingFixBatchResult {
+    /// Per-file results, in the order they were processed
+    pub results: Vec<ThinkingFixResult>,
+    /// Files that could not be processed at all (read/snapshot/write failures), with the
+    /// error each one hit
+    pub failures: Vec<ThinkingFixFailure>,
+    /// Number of JSONL files processed
+    pub total_files: usize,
+    /// Sum of `total_lines` across all processed files
+    pub total_lines: usize,
+    /// Sum of `modified_lines` across all processed files
+    pub total_modified_lines: usize,
+    /// Sum of `thinking_blocks_removed` across all processed files
+    pub total_thinking_blocks_removed: usize,
+    /// Sum of `errors` across all processed files
+    pub total_errors: usize,
+}
+
+/// A JSONL file that `fix_thinking_blocks_all` could not process at all
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThinkingFixFailure {
+    /// Path to the file that failed
+    pub jsonl_path: String,
+    /// The error returned by `scan_jsonl_file`
+    pub error: String,
+}
+
+/// Walk a project directory and collect every `.jsonl` file it contains
+///
+/// Deliberately ignore- and hidden-unaware: session files live under `~/.claude/projects`,
+/// not a source tree, so a stray `.gitignore`/`.ignore` rule on an ancestor directory must
+/// never hide a `.jsonl` file from the walk.
+fn collect_jsonl_files(project_dir: &PathBuf) -> Vec<PathBuf> {
+    let mut files: Vec<(PathBuf, SystemTime)> = WalkBuilder::new(project_dir)
+        .hidden(false)
+        .git_ignore(false)
+        .ignore(false)
+        .git_global(false)
+        .git_exclude(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().map(|e| e == "jsonl").unwrap_or(false))
+        .filter_map(|entry| {
+            let path = entry.path().to_path_buf();
+            let modified = fs::metadata(&path).ok()?.modified().ok()?;
+            Some((path, modified))
+        })
+        .collect();
+
+    files.sort_by(|a, b| b.1.cmp(&a.1));
+    files.into_iter().map(|(path, _)| path).collect()
+}
+
+/// Fix thinking blocks across every project under `~/.claude/projects` (or a chosen subset)
+///
+/// When `project_paths` is `None` or empty, every project directory is processed. When
+/// `all_files` is true, every `.jsonl` file in each project is processed rather than just
+/// the most recently modified one. When `retain_snapshots` is set and this is not a dry run,
+/// pruning runs once per project after all of that project's files are processed, excluding
+/// every snapshot this run just created — so a batch of M files with `retain_snapshots` less
+/// than M still keeps all M fresh originals recoverable instead of immediately deleting the
+/// ones it just took. On a dry run no snapshots are taken, so pruning is skipped entirely
+/// rather than deleting real prior archives down to `keep`. A project in which any file
+/// failed is not pruned either.
+#[tauri::command]
+pub async fn fix_thinking_blocks_all(
+    project_paths: Option<Vec<String>>,
+    all_files: bool,
+    dry_run: bool,
+    options: Option<FixOptions>,
+    retain_snapshots: Option<usize>,
+) -> Result<ThinkingFixBatchResult, String> {
+    let project_dirs: Vec<PathBuf> = match project_paths {
+        Some(paths) if !paths.is_empty() => paths.into_iter().map(PathBuf::from).collect(),
+        _ => {
+            let projects = get_claude_projects().await?;
+            projects.into_iter().map(|p| PathBuf::from(p.path)).collect()
+        }
+    };
+
+    let options = options.unwrap_or_default();
+    let mut results = Vec::new();
+    let mut failures = Vec::new();
+
+    for project_dir in &project_dirs {
+        if !project_dir.exists() {
+            continue;
+        }
+
+        let jsonl_files = if all_files {
+            collect_jsonl_files(project_dir)
+        } else {
+            find_latest_jsonl(project_dir).into_iter().collect()
+        };
+
+        let project_path = project_dir.to_string_lossy().to_string();
+        let mut archives_created_this_run = Vec::new();
+        let failures_before = failures.len();
+
+        for jsonl_path in jsonl_files {
+            match scan_jsonl_file(&jsonl_path, &project_path, &options, dry_run, dry_run) {
+                Ok(result) => {
+                    if let Some(archive_path) = &result.backup_path {
+                        archives_created_this_run.push(PathBuf::from(archive_path));
+                    }
+                    results.push(result);
+                }
+                Err(error) => failures.push(ThinkingFixFailure {
+                    jsonl_path: jsonl_path.to_string_lossy().to_string(),
+                    error,
+                }),
+            }
+        }
+
+        // A file that failed after its snapshot was written has no path to exclude, so a
+        // project with any failure is not pruned at all.
+        if !dry_run && failures.len() == failures_before {
+            if let Some(keep) = retain_snapshots {
+                snapshot::prune_old_snapshots(&project_path, keep, &archives_created_this_run)?;
+            }
+        }
+    }
+
+    let total_files = results.len();
+    let total_lines = results.iter().map(|r| r.total_lines).sum();
+    let total_modified_lines = results.iter().map(|r| r.modified_lines).sum();
+    let total_thinking_blocks_removed = results.iter().map(|r| r.thinking_blocks_removed).sum();
+    let total_errors = results.iter().map(|r| r.errors).sum();
+
+    Ok(ThinkingFixBatchResult {
+        results,
+        failures,
+        total_files,
+        total_lines,
+        total_modified_lines,
+        total_thinking_blocks_removed,
+        total_errors,
     })
 }